@@ -1,10 +1,21 @@
 //! A set of types and functions related to cryptography, that are widely used in the entire Simperby project.
+//!
+//! Requires `bs58` (Display/FromStr), `bip39` + `hmac` + `sha2` (HD key derivation),
+//! `ed25519-dalek` with the `batch` feature (batch verification), and `secp256k1` `>=0.28`
+//! (for `Message::from_digest_slice`) with the `recovery` and `rand-std` features plus `sha3` +
+//! `hex` (cross-chain settlement).
+use bip39::{Language, Mnemonic};
 use ed25519::signature::{Signer, Verifier};
-use rand::SeedableRng;
+use hmac::{Hmac, Mac};
+use rand::{RngCore, SeedableRng};
 use serde::{Deserialize, Serialize};
+use sha2::Sha512;
 use std::fmt;
+use std::str::FromStr;
 use thiserror::Error;
 
+type HmacSha512 = Hmac<Sha512>;
+
 #[derive(Error, Debug, Serialize, Deserialize, Clone)]
 pub enum CryptoError {
     /// When the data format is not valid.
@@ -53,7 +64,21 @@ impl std::convert::AsRef<[u8]> for Hash256 {
 
 impl fmt::Display for Hash256 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "?")
+        write!(f, "{}", bs58::encode(&self.hash).into_string())
+    }
+}
+
+impl FromStr for Hash256 {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = bs58::decode(s)
+            .into_vec()
+            .map_err(|_| Error::InvalidFormat(format!("hash: {}", s)))?;
+        let hash: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| Error::InvalidFormat(format!("hash: {}", s)))?;
+        Ok(Hash256::from_array(hash))
     }
 }
 
@@ -70,7 +95,6 @@ impl Signature {
         public_key: &PublicKey,
         private_key: &PrivateKey,
     ) -> Result<Self, Error> {
-        check_keypair_match(public_key, private_key)?;
         let public_key = ed25519_dalek::PublicKey::from_bytes(&public_key.key)
             .map_err(|_| Error::InvalidFormat(format!("public key: {}", public_key)))?;
         let private_key = ed25519_dalek::SecretKey::from_bytes(&private_key.key)
@@ -94,41 +118,135 @@ impl Signature {
             .verify(data.as_ref(), &signature)
             .map_err(|_| Error::VerificationFailed)
     }
+
+    /// Returns the raw bytes of the signature.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.signature.clone()
+    }
+
+    /// Constructs a signature from raw bytes, without any validation.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Self {
+        Signature {
+            signature: bytes.as_ref().to_vec(),
+        }
+    }
 }
 
-/// A signature that is explicitly marked with the type of the signed data.
-///
-/// This implies that the signature is created on `Hash256::hash(serde_json::to_vec(T).unwrap())`.
+/// A domain separator for the protocol phase a [`TypedSignature`] was produced for.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize, Deserialize, Hash)]
+pub enum SignaturePurpose {
+    Prevote,
+    Precommit,
+    BlockProposal,
+}
+
+impl SignaturePurpose {
+    /// The domain-separation tag mixed into the hash before signing/verifying.
+    fn tag(&self) -> &'static [u8] {
+        match self {
+            SignaturePurpose::Prevote => b"simperby/prevote",
+            SignaturePurpose::Precommit => b"simperby/precommit",
+            SignaturePurpose::BlockProposal => b"simperby/block-proposal",
+        }
+    }
+}
+
+/// A signature explicitly marked with the type of the signed data and its purpose.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
 pub struct TypedSignature<T> {
     pub signature: Signature,
+    pub purpose: SignaturePurpose,
     pub _mark: std::marker::PhantomData<T>,
 }
 
 impl<T: serde::Serialize> TypedSignature<T> {
-    /// Creates a new signature from the given data and keys.
-    pub fn sign(data: &T, public_key: &PublicKey, private_key: &PrivateKey) -> Result<Self, Error> {
-        let data = serde_json::to_vec(data).map_err(|_| Error::InvalidFormat("data".to_owned()))?;
-        let data = Hash256::hash(data);
-        Signature::sign(data, public_key, private_key).map(|signature| TypedSignature {
+    /// Creates a new signature from the given data, purpose, and keys.
+    pub fn sign(
+        data: &T,
+        purpose: SignaturePurpose,
+        public_key: &PublicKey,
+        private_key: &PrivateKey,
+    ) -> Result<Self, Error> {
+        let hash = Self::purpose_scoped_hash(data, purpose)?;
+        Signature::sign(hash, public_key, private_key).map(|signature| TypedSignature {
             signature,
+            purpose,
             _mark: std::marker::PhantomData,
         })
     }
 
-    pub fn new(signature: Signature) -> Self {
+    pub fn new(signature: Signature, purpose: SignaturePurpose) -> Self {
         TypedSignature {
             signature,
+            purpose,
             _mark: std::marker::PhantomData,
         }
     }
 
-    /// Verifies the signature against the given data and public key.
-    pub fn verify(&self, data: &T, public_key: &PublicKey) -> Result<(), Error> {
+    /// Verifies the signature against the given data, purpose, and public key.
+    ///
+    /// Fails with [`CryptoError::VerificationFailed`] if `purpose` doesn't match the purpose
+    /// this signature was created for, even if the underlying ed25519 signature is valid.
+    pub fn verify(
+        &self,
+        data: &T,
+        purpose: SignaturePurpose,
+        public_key: &PublicKey,
+    ) -> Result<(), Error> {
+        if self.purpose != purpose {
+            return Err(Error::VerificationFailed);
+        }
+        let hash = Self::purpose_scoped_hash(data, purpose)?;
+        self.signature.verify(hash, public_key)
+    }
+
+    fn purpose_scoped_hash(data: &T, purpose: SignaturePurpose) -> Result<Hash256, Error> {
         let data = serde_json::to_vec(data).map_err(|_| Error::InvalidFormat("data".to_owned()))?;
-        let data = Hash256::hash(data);
-        self.signature.verify(data, public_key)
+        Ok(Hash256::hash([purpose.tag(), data.as_slice()].concat()))
+    }
+}
+
+/// Verifies many `(hash, signature, public key)` triples at once.
+pub fn verify_batch(items: &[(Hash256, &Signature, &PublicKey)]) -> Result<(), Error> {
+    let mut messages = Vec::with_capacity(items.len());
+    let mut signatures = Vec::with_capacity(items.len());
+    let mut public_keys = Vec::with_capacity(items.len());
+    for (hash, signature, public_key) in items {
+        messages.push(hash.hash.as_ref());
+        signatures.push(
+            ed25519_dalek::Signature::from_bytes(&signature.signature)
+                .map_err(|_| Error::InvalidFormat(format!("signature: {}", signature)))?,
+        );
+        public_keys.push(
+            ed25519_dalek::PublicKey::from_bytes(&public_key.key)
+                .map_err(|_| Error::InvalidFormat(format!("public_key: {}", public_key)))?,
+        );
+    }
+    ed25519_dalek::verify_batch(&messages, &signatures, &public_keys)
+        .map_err(|_| Error::VerificationFailed)
+}
+
+/// Re-verifies each triple individually, to pinpoint the index of a failed [`verify_batch`].
+pub fn verify_batch_fallback(items: &[(Hash256, &Signature, &PublicKey)]) -> Result<(), (usize, Error)> {
+    for (i, (hash, signature, public_key)) in items.iter().enumerate() {
+        signature.verify(hash.clone(), public_key).map_err(|e| (i, e))?;
     }
+    Ok(())
+}
+
+/// Verifies many [`TypedSignature`]s at once, purpose-scoping and hashing each `data` first.
+pub fn verify_typed_batch<T: serde::Serialize>(
+    items: &[(&T, SignaturePurpose, &TypedSignature<T>, &PublicKey)],
+) -> Result<(), Error> {
+    let mut hashed = Vec::with_capacity(items.len());
+    for (data, purpose, signature, public_key) in items {
+        if signature.purpose != *purpose {
+            return Err(Error::VerificationFailed);
+        }
+        let hash = TypedSignature::<T>::purpose_scoped_hash(data, *purpose)?;
+        hashed.push((hash, &signature.signature, *public_key));
+    }
+    verify_batch(&hashed)
 }
 
 impl std::convert::AsRef<[u8]> for Signature {
@@ -139,7 +257,18 @@ impl std::convert::AsRef<[u8]> for Signature {
 
 impl fmt::Display for Signature {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "?")
+        write!(f, "{}", bs58::encode(&self.signature).into_string())
+    }
+}
+
+impl FromStr for Signature {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = bs58::decode(s)
+            .into_vec()
+            .map_err(|_| Error::InvalidFormat(format!("signature: {}", s)))?;
+        Ok(Signature::from_bytes(bytes))
     }
 }
 
@@ -157,7 +286,76 @@ impl std::convert::AsRef<[u8]> for PublicKey {
 
 impl fmt::Display for PublicKey {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "?")
+        write!(f, "{}", bs58::encode(&self.key).into_string())
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = bs58::decode(s)
+            .into_vec()
+            .map_err(|_| Error::InvalidFormat(format!("public key: {}", s)))?;
+        Ok(PublicKey::from_bytes(bytes))
+    }
+}
+
+impl PublicKey {
+    /// Returns the raw bytes of the public key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.key.clone()
+    }
+
+    /// Constructs a public key from raw bytes, without any validation.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Self {
+        PublicKey {
+            key: bytes.as_ref().to_vec(),
+        }
+    }
+}
+
+/// A [`PublicKey`] that has already been decoded into an `ed25519_dalek` point, cached for reuse.
+#[derive(Debug, Clone)]
+pub struct CachedPublicKey {
+    bytes: PublicKey,
+    decoded: ed25519_dalek::PublicKey,
+}
+
+impl CachedPublicKey {
+    /// Verifies the signature against the given data, using the cached decoded key.
+    pub fn verify(&self, data: Hash256, signature: &Signature) -> Result<(), Error> {
+        let signature = ed25519_dalek::Signature::from_bytes(&signature.signature)
+            .map_err(|_| Error::InvalidFormat(format!("signature: {}", signature)))?;
+        self.decoded
+            .verify(data.as_ref(), &signature)
+            .map_err(|_| Error::VerificationFailed)
+    }
+
+    /// Returns the wire-format public key this was decoded from.
+    pub fn as_bytes_form(&self) -> &PublicKey {
+        &self.bytes
+    }
+}
+
+impl std::convert::TryFrom<&PublicKey> for CachedPublicKey {
+    type Error = Error;
+
+    fn try_from(public_key: &PublicKey) -> Result<Self, Error> {
+        let decoded = ed25519_dalek::PublicKey::from_bytes(&public_key.key)
+            .map_err(|_| Error::InvalidFormat(format!("public key: {}", public_key)))?;
+        Ok(CachedPublicKey {
+            bytes: public_key.clone(),
+            decoded,
+        })
+    }
+}
+
+impl std::convert::TryFrom<PublicKey> for CachedPublicKey {
+    type Error = Error;
+
+    fn try_from(public_key: PublicKey) -> Result<Self, Error> {
+        CachedPublicKey::try_from(&public_key)
     }
 }
 
@@ -173,6 +371,37 @@ impl std::convert::AsRef<[u8]> for PrivateKey {
     }
 }
 
+impl fmt::Display for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[redacted]")
+    }
+}
+
+impl FromStr for PrivateKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = bs58::decode(s)
+            .into_vec()
+            .map_err(|_| Error::InvalidFormat("private key: [omitted]".to_owned()))?;
+        Ok(PrivateKey::from_bytes(bytes))
+    }
+}
+
+impl PrivateKey {
+    /// Returns the raw bytes of the private key.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.key.clone()
+    }
+
+    /// Constructs a private key from raw bytes, without any validation.
+    pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Self {
+        PrivateKey {
+            key: bytes.as_ref().to_vec(),
+        }
+    }
+}
+
 /// Checkes whether the given public and private keys match.
 pub fn check_keypair_match(public_key: &PublicKey, private_key: &PrivateKey) -> Result<(), Error> {
     let msg = "Some Random Message".as_bytes();
@@ -197,3 +426,525 @@ pub fn generate_keypair(seed: impl AsRef<[u8]>) -> (PublicKey, PrivateKey) {
         },
     )
 }
+
+/// Generates a new random BIP39 mnemonic phrase (English wordlist, 256 bits of entropy),
+/// which can later be turned into an arbitrary number of keypairs with [`derive_keypair`].
+pub fn generate_mnemonic() -> String {
+    let mut entropy = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut entropy);
+    Mnemonic::from_entropy_in(Language::English, &entropy)
+        .expect("32 bytes is a valid BIP39 entropy length")
+        .to_string()
+}
+
+/// Derives the 32-byte ed25519 master key and chain code from a BIP39 seed,
+/// following the SLIP-0010 specification.
+fn slip10_ed25519_master_key(seed: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let mut mac =
+        HmacSha512::new_from_slice(b"ed25519 seed").expect("hmac accepts a key of any length");
+    mac.update(seed);
+    let result = mac.finalize().into_bytes();
+    let mut key = [0u8; 32];
+    let mut chain_code = [0u8; 32];
+    key.copy_from_slice(&result[0..32]);
+    chain_code.copy_from_slice(&result[32..64]);
+    (key, chain_code)
+}
+
+/// Derives the hardened SLIP-0010 ed25519 child key and chain code at the given index.
+///
+/// ed25519 only supports hardened derivation, so `index` is always treated as hardened
+/// regardless of whether its high bit is already set.
+fn slip10_ed25519_child_key(key: &[u8; 32], chain_code: &[u8; 32], index: u32) -> ([u8; 32], [u8; 32]) {
+    let hardened_index = index | 0x8000_0000;
+    let mut mac = HmacSha512::new_from_slice(chain_code).expect("hmac accepts a key of any length");
+    mac.update(&[0x00]);
+    mac.update(key);
+    mac.update(&hardened_index.to_be_bytes());
+    let result = mac.finalize().into_bytes();
+    let mut child_key = [0u8; 32];
+    let mut child_chain_code = [0u8; 32];
+    child_key.copy_from_slice(&result[0..32]);
+    child_chain_code.copy_from_slice(&result[32..64]);
+    (child_key, child_chain_code)
+}
+
+/// Parses a BIP32-style path such as `m/44'/1'/0'` into a sequence of child indices.
+///
+/// SLIP-0010 for ed25519 only supports hardened derivation, so the `'`/`h` hardened
+/// marker is optional and implied on every component.
+fn parse_derivation_path(path: &str) -> Result<Vec<u32>, Error> {
+    let mut components = path.split('/');
+    match components.next() {
+        Some("m") => {}
+        _ => return Err(Error::InvalidFormat(format!("derivation path: {}", path))),
+    }
+    components
+        .map(|component| {
+            let component = component.trim_end_matches('\'').trim_end_matches('h');
+            component
+                .parse::<u32>()
+                .map_err(|_| Error::InvalidFormat(format!("derivation path: {}", path)))
+        })
+        .collect()
+}
+
+/// Derives an ed25519 keypair from a BIP39 mnemonic phrase, an optional passphrase, and a
+/// SLIP-0010 derivation path (e.g. `m/44'/1'/0'`), so that an entire validator key hierarchy
+/// can be regenerated from a single backup phrase.
+pub fn derive_keypair(
+    mnemonic: &str,
+    passphrase: Option<&str>,
+    path: &str,
+) -> Result<(PublicKey, PrivateKey), Error> {
+    let mnemonic = Mnemonic::parse_in(Language::English, mnemonic)
+        .map_err(|e| Error::InvalidFormat(format!("mnemonic: {}", e)))?;
+    let seed = mnemonic.to_seed(passphrase.unwrap_or(""));
+    let indices = parse_derivation_path(path)?;
+    let (mut key, mut chain_code) = slip10_ed25519_master_key(&seed);
+    for index in indices {
+        let (child_key, child_chain_code) = slip10_ed25519_child_key(&key, &chain_code, index);
+        key = child_key;
+        chain_code = child_chain_code;
+    }
+    let secret = ed25519_dalek::SecretKey::from_bytes(&key)
+        .map_err(|_| Error::InvalidFormat("derived private key".to_owned()))?;
+    let public = ed25519_dalek::PublicKey::from(&secret);
+    Ok((
+        PublicKey {
+            key: public.to_bytes().to_vec(),
+        },
+        PrivateKey {
+            key: secret.to_bytes().to_vec(),
+        },
+    ))
+}
+
+/// Ethereum-compatible secp256k1 ECDSA signatures and address recovery.
+pub mod secp256k1 {
+    use super::{CryptoError as Error, Hash256};
+    use sha3::{Digest, Keccak256};
+    use std::fmt;
+    use std::str::FromStr;
+
+    /// A secp256k1 public key, in 33-byte SEC1 compressed form.
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+    pub struct PublicKey {
+        key: Vec<u8>,
+    }
+
+    impl PublicKey {
+        pub fn to_bytes(&self) -> Vec<u8> {
+            self.key.clone()
+        }
+
+        pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Self {
+            PublicKey {
+                key: bytes.as_ref().to_vec(),
+            }
+        }
+    }
+
+    /// A secp256k1 private key (32-byte scalar).
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+    pub struct PrivateKey {
+        key: Vec<u8>,
+    }
+
+    impl PrivateKey {
+        pub fn to_bytes(&self) -> Vec<u8> {
+            self.key.clone()
+        }
+
+        pub fn from_bytes(bytes: impl AsRef<[u8]>) -> Self {
+            PrivateKey {
+                key: bytes.as_ref().to_vec(),
+            }
+        }
+    }
+
+    /// A 20-byte Ethereum-style address, derived from the Keccak-256 hash of an uncompressed
+    /// public key (see [`public_to_address`]).
+    #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+    pub struct Address([u8; 20]);
+
+    impl Address {
+        pub fn to_bytes(&self) -> [u8; 20] {
+            self.0
+        }
+
+        pub fn from_bytes(bytes: [u8; 20]) -> Self {
+            Address(bytes)
+        }
+    }
+
+    impl fmt::Display for Address {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "0x{}", hex::encode(self.0))
+        }
+    }
+
+    impl FromStr for Address {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let bytes = hex::decode(s.trim_start_matches("0x"))
+                .map_err(|_| Error::InvalidFormat(format!("address: {}", s)))?;
+            let bytes: [u8; 20] = bytes
+                .try_into()
+                .map_err(|_| Error::InvalidFormat(format!("address: {}", s)))?;
+            Ok(Address(bytes))
+        }
+    }
+
+    /// A 65-byte recoverable ECDSA signature: `r`, `s`, and a recovery id `v`.
+    #[derive(Debug, PartialEq, Eq, Clone)]
+    pub struct RecoverableSignature {
+        bytes: [u8; 65],
+    }
+
+    impl RecoverableSignature {
+        pub fn r(&self) -> [u8; 32] {
+            self.bytes[0..32].try_into().unwrap()
+        }
+
+        pub fn s(&self) -> [u8; 32] {
+            self.bytes[32..64].try_into().unwrap()
+        }
+
+        pub fn v(&self) -> u8 {
+            self.bytes[64]
+        }
+
+        pub fn to_bytes(&self) -> [u8; 65] {
+            self.bytes
+        }
+
+        pub fn from_bytes(bytes: [u8; 65]) -> Self {
+            RecoverableSignature { bytes }
+        }
+    }
+
+    impl fmt::Display for RecoverableSignature {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "0x{}", hex::encode(self.bytes))
+        }
+    }
+
+    impl FromStr for RecoverableSignature {
+        type Err = Error;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let bytes = hex::decode(s.trim_start_matches("0x"))
+                .map_err(|_| Error::InvalidFormat(format!("recoverable signature: {}", s)))?;
+            let bytes: [u8; 65] = bytes
+                .try_into()
+                .map_err(|_| Error::InvalidFormat(format!("recoverable signature: {}", s)))?;
+            Ok(RecoverableSignature { bytes })
+        }
+    }
+
+    /// Signs `hash` with `private_key`, producing a recoverable signature.
+    pub fn sign_recoverable(hash: Hash256, private_key: &PrivateKey) -> Result<RecoverableSignature, Error> {
+        let secp = ::secp256k1::Secp256k1::signing_only();
+        let secret_key = ::secp256k1::SecretKey::from_slice(&private_key.key)
+            .map_err(|_| Error::InvalidFormat("private key: [omitted]".to_owned()))?;
+        let message = ::secp256k1::Message::from_digest_slice(hash.as_ref())
+            .map_err(|_| Error::InvalidFormat(format!("hash: {}", hash)))?;
+        let (recovery_id, signature) = secp
+            .sign_ecdsa_recoverable(&message, &secret_key)
+            .serialize_compact();
+        let mut bytes = [0u8; 65];
+        bytes[0..64].copy_from_slice(&signature);
+        bytes[64] = recovery_id.to_i32() as u8;
+        Ok(RecoverableSignature { bytes })
+    }
+
+    /// Reconstructs the signer's public key from the message hash and a recoverable signature.
+    pub fn recover_public_key(hash: Hash256, signature: &RecoverableSignature) -> Result<PublicKey, Error> {
+        let secp = ::secp256k1::Secp256k1::verification_only();
+        let message = ::secp256k1::Message::from_digest_slice(hash.as_ref())
+            .map_err(|_| Error::InvalidFormat(format!("hash: {}", hash)))?;
+        let recovery_id = ::secp256k1::ecdsa::RecoveryId::from_i32(signature.v() as i32)
+            .map_err(|_| Error::InvalidFormat(format!("recoverable signature: {}", signature)))?;
+        let recoverable_signature =
+            ::secp256k1::ecdsa::RecoverableSignature::from_compact(&signature.bytes[0..64], recovery_id)
+                .map_err(|_| Error::InvalidFormat(format!("recoverable signature: {}", signature)))?;
+        let public_key = secp
+            .recover_ecdsa(&message, &recoverable_signature)
+            .map_err(|_| Error::VerificationFailed)?;
+        Ok(PublicKey {
+            key: public_key.serialize().to_vec(),
+        })
+    }
+
+    /// Derives the Ethereum address of `public_key`: the low 20 bytes of the Keccak-256 hash of
+    /// its uncompressed (64-byte, no prefix) encoding.
+    pub fn public_to_address(public_key: &PublicKey) -> Result<Address, Error> {
+        let key = ::secp256k1::PublicKey::from_slice(&public_key.key)
+            .map_err(|_| Error::InvalidFormat(format!("public key: {:?}", public_key)))?;
+        let uncompressed = key.serialize_uncompressed();
+        // Drop the leading 0x04 prefix before hashing, as Ethereum does.
+        let hash = Keccak256::digest(&uncompressed[1..]);
+        let mut address = [0u8; 20];
+        address.copy_from_slice(&hash[12..32]);
+        Ok(Address(address))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn generate_keypair() -> (PublicKey, PrivateKey) {
+            let secp = ::secp256k1::Secp256k1::new();
+            let mut rng = ::secp256k1::rand::thread_rng();
+            let (secret_key, public_key) = secp.generate_keypair(&mut rng);
+            (
+                PublicKey {
+                    key: public_key.serialize().to_vec(),
+                },
+                PrivateKey {
+                    key: secret_key.secret_bytes().to_vec(),
+                },
+            )
+        }
+
+        #[test]
+        fn recover_public_key_round_trip() {
+            let (public_key, private_key) = generate_keypair();
+            let hash = Hash256::hash("settlement message");
+            let signature = sign_recoverable(hash.clone(), &private_key).unwrap();
+            let recovered = recover_public_key(hash, &signature).unwrap();
+            assert_eq!(public_key, recovered);
+        }
+
+        #[test]
+        fn recoverable_signature_to_string_from_str_round_trip() {
+            let (_, private_key) = generate_keypair();
+            let hash = Hash256::hash("settlement message");
+            let signature = sign_recoverable(hash, &private_key).unwrap();
+            let parsed: RecoverableSignature = signature.to_string().parse().unwrap();
+            assert_eq!(signature, parsed);
+        }
+
+        #[test]
+        fn public_to_address_is_stable_for_the_same_key() {
+            let (public_key, _) = generate_keypair();
+            assert_eq!(
+                public_to_address(&public_key).unwrap(),
+                public_to_address(&public_key).unwrap()
+            );
+        }
+
+        #[test]
+        fn address_to_string_from_str_round_trip() {
+            let (public_key, _) = generate_keypair();
+            let address = public_to_address(&public_key).unwrap();
+            let parsed: Address = address.to_string().parse().unwrap();
+            assert_eq!(address, parsed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash256_to_string_from_str_round_trip() {
+        let hash = Hash256::hash("hello world");
+        let parsed: Hash256 = hash.to_string().parse().unwrap();
+        assert_eq!(hash, parsed);
+    }
+
+    #[test]
+    fn public_key_to_string_from_str_round_trip() {
+        let (public_key, _) = generate_keypair("seed");
+        let parsed: PublicKey = public_key.to_string().parse().unwrap();
+        assert_eq!(public_key, parsed);
+    }
+
+    #[test]
+    fn private_key_from_str_round_trips_through_bytes() {
+        let (_, private_key) = generate_keypair("seed");
+        let encoded = bs58::encode(private_key.to_bytes()).into_string();
+        let parsed: PrivateKey = encoded.parse().unwrap();
+        assert_eq!(private_key, parsed);
+    }
+
+    #[test]
+    fn private_key_display_does_not_leak_the_key() {
+        let (_, private_key) = generate_keypair("seed");
+        assert_eq!(private_key.to_string(), "[redacted]");
+    }
+
+    #[test]
+    fn signature_to_string_from_str_round_trip() {
+        let (public_key, private_key) = generate_keypair("seed");
+        let data = Hash256::hash("message");
+        let signature = Signature::sign(data, &public_key, &private_key).unwrap();
+        let parsed: Signature = signature.to_string().parse().unwrap();
+        assert_eq!(signature, parsed);
+    }
+
+    #[test]
+    fn public_key_to_bytes_from_bytes_round_trip() {
+        let (public_key, _) = generate_keypair("seed");
+        let parsed = PublicKey::from_bytes(public_key.to_bytes());
+        assert_eq!(public_key, parsed);
+    }
+
+    #[test]
+    fn private_key_to_bytes_from_bytes_round_trip() {
+        let (_, private_key) = generate_keypair("seed");
+        let parsed = PrivateKey::from_bytes(private_key.to_bytes());
+        assert_eq!(private_key, parsed);
+    }
+
+    #[test]
+    fn signature_to_bytes_from_bytes_round_trip() {
+        let (public_key, private_key) = generate_keypair("seed");
+        let data = Hash256::hash("message");
+        let signature = Signature::sign(data, &public_key, &private_key).unwrap();
+        let parsed = Signature::from_bytes(signature.to_bytes());
+        assert_eq!(signature, parsed);
+    }
+
+    #[test]
+    fn derive_keypair_is_deterministic() {
+        let mnemonic = generate_mnemonic();
+        let keypair_1 = derive_keypair(&mnemonic, None, "m/44'/1'/0'").unwrap();
+        let keypair_2 = derive_keypair(&mnemonic, None, "m/44'/1'/0'").unwrap();
+        assert_eq!(keypair_1, keypair_2);
+    }
+
+    #[test]
+    fn derive_keypair_differs_per_path_and_passphrase() {
+        let mnemonic = generate_mnemonic();
+        let account_0 = derive_keypair(&mnemonic, None, "m/44'/1'/0'").unwrap();
+        let account_1 = derive_keypair(&mnemonic, None, "m/44'/1'/1'").unwrap();
+        let with_passphrase = derive_keypair(&mnemonic, Some("extra"), "m/44'/1'/0'").unwrap();
+        assert_ne!(account_0, account_1);
+        assert_ne!(account_0, with_passphrase);
+    }
+
+    #[test]
+    fn derive_keypair_produces_a_valid_keypair() {
+        let mnemonic = generate_mnemonic();
+        let (public_key, private_key) = derive_keypair(&mnemonic, None, "m/44'/1'/0'").unwrap();
+        check_keypair_match(&public_key, &private_key).unwrap();
+    }
+
+    #[test]
+    fn typed_signature_verifies_with_matching_purpose() {
+        let (public_key, private_key) = generate_keypair("seed");
+        let data = "block data".to_owned();
+        let signature =
+            TypedSignature::sign(&data, SignaturePurpose::BlockProposal, &public_key, &private_key)
+                .unwrap();
+        signature
+            .verify(&data, SignaturePurpose::BlockProposal, &public_key)
+            .unwrap();
+    }
+
+    #[test]
+    fn typed_signature_rejects_mismatched_purpose() {
+        let (public_key, private_key) = generate_keypair("seed");
+        let data = "vote data".to_owned();
+        let signature =
+            TypedSignature::sign(&data, SignaturePurpose::Prevote, &public_key, &private_key)
+                .unwrap();
+        assert!(signature
+            .verify(&data, SignaturePurpose::Precommit, &public_key)
+            .is_err());
+    }
+
+    #[test]
+    fn verify_batch_accepts_all_valid_signatures() {
+        let keypairs: Vec<_> = (0..4).map(|i| generate_keypair(format!("seed{i}"))).collect();
+        let hashes: Vec<_> = (0..4)
+            .map(|i| Hash256::hash(format!("message{i}")))
+            .collect();
+        let signatures: Vec<_> = keypairs
+            .iter()
+            .zip(&hashes)
+            .map(|((public_key, private_key), hash)| {
+                Signature::sign(hash.clone(), public_key, private_key).unwrap()
+            })
+            .collect();
+        let items: Vec<_> = hashes
+            .iter()
+            .zip(&signatures)
+            .zip(&keypairs)
+            .map(|((hash, signature), (public_key, _))| (hash.clone(), signature, public_key))
+            .collect();
+        verify_batch(&items).unwrap();
+    }
+
+    #[test]
+    fn verify_batch_fallback_pinpoints_the_bad_signature() {
+        let (public_key_1, private_key_1) = generate_keypair("seed1");
+        let (public_key_2, _private_key_2) = generate_keypair("seed2");
+        let hash_1 = Hash256::hash("message1");
+        let hash_2 = Hash256::hash("message2");
+        let signature_1 = Signature::sign(hash_1.clone(), &public_key_1, &private_key_1).unwrap();
+        // Signed under the wrong key, so verification of this entry must fail.
+        let bad_signature_2 = Signature::sign(hash_2.clone(), &public_key_1, &private_key_1).unwrap();
+        let items = [
+            (hash_1, &signature_1, &public_key_1),
+            (hash_2, &bad_signature_2, &public_key_2),
+        ];
+        assert!(verify_batch(&items).is_err());
+        let (index, _) = verify_batch_fallback(&items).unwrap_err();
+        assert_eq!(index, 1);
+    }
+
+    #[test]
+    fn verify_typed_batch_accepts_all_valid_signatures() {
+        let keypairs: Vec<_> = (0..4).map(|i| generate_keypair(format!("seed{i}"))).collect();
+        let data: Vec<_> = (0..4).map(|i| format!("message{i}")).collect();
+        let signatures: Vec<_> = keypairs
+            .iter()
+            .zip(&data)
+            .map(|((public_key, private_key), data)| {
+                TypedSignature::sign(data, SignaturePurpose::Prevote, public_key, private_key).unwrap()
+            })
+            .collect();
+        let items: Vec<_> = data
+            .iter()
+            .zip(&signatures)
+            .zip(&keypairs)
+            .map(|((data, signature), (public_key, _))| {
+                (data, SignaturePurpose::Prevote, signature, public_key)
+            })
+            .collect();
+        verify_typed_batch(&items).unwrap();
+    }
+
+    #[test]
+    fn verify_typed_batch_rejects_a_mismatched_purpose() {
+        let (public_key, private_key) = generate_keypair("seed");
+        let data = "vote data".to_owned();
+        let signature =
+            TypedSignature::sign(&data, SignaturePurpose::Prevote, &public_key, &private_key).unwrap();
+        let items = [(&data, SignaturePurpose::Precommit, &signature, &public_key)];
+        assert!(verify_typed_batch(&items).is_err());
+    }
+
+    #[test]
+    fn cached_public_key_verifies_like_the_plain_key() {
+        let (public_key, private_key) = generate_keypair("seed");
+        let data = Hash256::hash("message");
+        let signature = Signature::sign(data.clone(), &public_key, &private_key).unwrap();
+        let cached_key: CachedPublicKey = (&public_key).try_into().unwrap();
+        cached_key.verify(data, &signature).unwrap();
+        assert_eq!(cached_key.as_bytes_form(), &public_key);
+    }
+
+    #[test]
+    fn cached_public_key_rejects_invalid_bytes() {
+        let invalid = PublicKey::from_bytes(vec![0u8; 4]);
+        let result: Result<CachedPublicKey, _> = (&invalid).try_into();
+        assert!(result.is_err());
+    }
+}